@@ -1,10 +1,17 @@
 use chrono::NaiveDate;
+use log::LevelFilter;
 use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::collections::VecDeque;
 use std::error::Error;
+use std::fmt;
 use std::fs::File;
-use std::io::{stdout, Write};
+use std::io::{stdout, Read, Write};
 use std::iter::Iterator;
+use std::os::unix::process::CommandExt;
 use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Condvar, Mutex};
 use std::time::Instant;
 use std::{env, fs};
 
@@ -14,29 +21,283 @@ pub type Result<T> = std::result::Result<T, Box<dyn Error>>;
 pub struct Verdict {
     pub ok: bool,
     pub msg: Option<String>,
+    /// Peak resident set size observed while producing this verdict, in KiB,
+    /// when the check ran the solution through [`run_solution`].
+    pub peak_memory_kb: Option<u64>,
 }
 
+impl Verdict {
+    pub fn with_peak_memory(mut self, peak_memory_kb: u64) -> Self {
+        self.peak_memory_kb = Some(peak_memory_kb);
+        self
+    }
+}
+
+/// The acronym reported for a test case, shared between the human-readable
+/// `result.txt` and the machine-readable `result.json`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum VerdictKind {
+    Ac,
+    Wa,
+    Tle,
+    Mle,
+    Re,
+}
+
+impl fmt::Display for VerdictKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let acr = match self {
+            VerdictKind::Ac => "AC",
+            VerdictKind::Wa => "WA",
+            VerdictKind::Tle => "TLE",
+            VerdictKind::Mle => "MLE",
+            VerdictKind::Re => "RE",
+        };
+        f.write_str(acr)
+    }
+}
+
+/// A single test case's entry in `result.json`.
+#[derive(Debug, Clone, Serialize)]
+pub struct CaseReport {
+    pub index: usize,
+    pub verdict: VerdictKind,
+    pub wall_time: f64,
+    pub cpu_time: f64,
+    pub peak_memory_kb: Option<u64>,
+    pub subtask: Option<String>,
+    pub message: Option<String>,
+}
+
+/// A named group of test cases worth `points` in total, scored IOI-style:
+/// the group earns its points only if every case in it passes, and zero
+/// otherwise (the minimum of the per-case pass/fail fractions).
 #[derive(Debug, Clone)]
+pub struct Subtask {
+    pub name: String,
+    pub points: f64,
+}
+
+/// The outcome of scoring a single [`Subtask`] group.
+#[derive(Debug, Clone, Serialize)]
+pub struct SubtaskScore {
+    pub name: String,
+    pub points: f64,
+    pub achieved: f64,
+    pub all_passed: bool,
+}
+
+/// Top-level summary written alongside the per-case reports in
+/// `result.json`.
+#[derive(Debug, Clone, Serialize)]
+pub struct RunSummary {
+    pub tc_ok: usize,
+    pub tc_n: usize,
+    pub multiplier: Option<u64>,
+    pub subtask_scores: Vec<SubtaskScore>,
+    pub cases: Vec<CaseReport>,
+}
+
+/// Which timing source decides whether a test case is a TLE.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeSource {
+    Wall,
+    Cpu,
+}
+
+/// A source of timing information, injectable so tests can drive the harness
+/// with scripted durations instead of the real system clock.
+pub trait Clock: Send + Sync {
+    /// Monotonic wall-clock reading, in seconds since an arbitrary epoch.
+    fn now(&self) -> f64;
+    /// CPU time consumed by the calling thread so far, in seconds.
+    fn cpu_time(&self) -> f64;
+}
+
+/// Production clock backed by [`Instant`] for wall time and
+/// `CLOCK_THREAD_CPUTIME_ID` for CPU time.
+///
+/// CPU time is read per-thread rather than process-wide: once test cases run
+/// concurrently (`RunOptions::parallelism > 1`), each worker thread takes its
+/// own before/after delta, and a process-wide clock would have every
+/// in-flight case's delta contaminated by CPU burned on sibling threads.
+pub struct MonotonicClock {
+    start: Instant,
+}
+
+impl MonotonicClock {
+    pub fn new() -> Self {
+        MonotonicClock {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Default for MonotonicClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for MonotonicClock {
+    fn now(&self) -> f64 {
+        self.start.elapsed().as_secs_f64()
+    }
+
+    fn cpu_time(&self) -> f64 {
+        thread_cpu_time_secs()
+    }
+}
+
+fn thread_cpu_time_secs() -> f64 {
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // SAFETY: `ts` is a valid out-pointer for a simple time query.
+    unsafe {
+        libc::clock_gettime(libc::CLOCK_THREAD_CPUTIME_ID, &mut ts);
+    }
+    ts.tv_sec as f64 + ts.tv_nsec as f64 / 1e9
+}
+
+/// Test clock that replays scripted wall/CPU readings instead of querying the
+/// system, so TLE decisions can be driven deterministically from a test.
+pub struct TestClock {
+    wall: Mutex<VecDeque<f64>>,
+    cpu: Mutex<VecDeque<f64>>,
+}
+
+impl TestClock {
+    pub fn new(wall: Vec<f64>, cpu: Vec<f64>) -> Self {
+        TestClock {
+            wall: Mutex::new(wall.into()),
+            cpu: Mutex::new(cpu.into()),
+        }
+    }
+}
+
+impl Clock for TestClock {
+    fn now(&self) -> f64 {
+        self.wall.lock().unwrap().pop_front().unwrap_or(0.0)
+    }
+
+    fn cpu_time(&self) -> f64 {
+        self.cpu.lock().unwrap().pop_front().unwrap_or(0.0)
+    }
+}
+
+#[derive(Clone)]
 pub struct RunOptions {
     pub time_limit: f64,
     pub public_wall_time: bool,
+    pub time_source: TimeSource,
+    pub clock: Arc<dyn Clock>,
+    /// Number of test cases allowed to run concurrently.
+    pub parallelism: usize,
+    /// Memory cap, in KiB, checked against a verdict's `peak_memory_kb` to
+    /// emit MLE. It is not passed to [`run_solution`], which takes its own
+    /// `memory_limit_kb` argument.
+    pub memory_limit_kb: Option<u64>,
+    /// Whether to write a structured `result.json` alongside `result.txt`.
+    pub json_output: bool,
+    /// IOI-style subtask groups to score, in declaration order. Empty means
+    /// the legacy raw-AC-count scoring is used instead.
+    pub subtasks: Vec<Subtask>,
+    /// Overrides the `log` crate's max level for this run. Falls back to the
+    /// `TAL_LOG_LEVEL` environment variable, then to whatever the host
+    /// process already configured.
+    pub log_level: Option<LevelFilter>,
+}
+
+impl fmt::Debug for RunOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RunOptions")
+            .field("time_limit", &self.time_limit)
+            .field("public_wall_time", &self.public_wall_time)
+            .field("time_source", &self.time_source)
+            .field("parallelism", &self.parallelism)
+            .field("memory_limit_kb", &self.memory_limit_kb)
+            .field("json_output", &self.json_output)
+            .field("subtasks", &self.subtasks)
+            .field("log_level", &self.log_level)
+            .finish()
+    }
+}
+
+/// A counting semaphore used to bound how many test cases run at once: each
+/// in-flight case holds one token, acquired before dispatch and released on
+/// completion, so the runner never oversubscribes the host's cores.
+struct TokenPool {
+    tokens: Mutex<usize>,
+    available: Condvar,
+}
+
+impl TokenPool {
+    fn new(tokens: usize) -> Self {
+        TokenPool {
+            tokens: Mutex::new(tokens.max(1)),
+            available: Condvar::new(),
+        }
+    }
+
+    /// Blocks until a token is available and returns a guard that releases
+    /// it back to the pool on drop, including on an unwinding panic, so a
+    /// panicking `gen_fn`/`check_fn` can't leak the token and deadlock every
+    /// later `acquire`.
+    fn acquire(&self) -> TokenGuard<'_> {
+        let mut tokens = self.tokens.lock().unwrap();
+        while *tokens == 0 {
+            tokens = self.available.wait(tokens).unwrap();
+        }
+        *tokens -= 1;
+        TokenGuard { pool: self }
+    }
+
+    fn release(&self) {
+        *self.tokens.lock().unwrap() += 1;
+        self.available.notify_one();
+    }
+}
+
+struct TokenGuard<'a> {
+    pool: &'a TokenPool,
+}
+
+impl Drop for TokenGuard<'_> {
+    fn drop(&mut self) {
+        self.pool.release();
+    }
 }
 
 impl From<bool> for Verdict {
     fn from(ok: bool) -> Self {
-        Verdict { ok, msg: None }
+        Verdict {
+            ok,
+            msg: None,
+            peak_memory_kb: None,
+        }
     }
 }
 
 impl From<(bool, Option<String>)> for Verdict {
     fn from((ok, msg): (bool, Option<String>)) -> Self {
-        Verdict { ok, msg }
+        Verdict {
+            ok,
+            msg,
+            peak_memory_kb: None,
+        }
     }
 }
 
 impl From<(bool, String)> for Verdict {
     fn from((ok, msg): (bool, String)) -> Self {
-        Verdict { ok, msg: Some(msg) }
+        Verdict {
+            ok,
+            msg: Some(msg),
+            peak_memory_kb: None,
+        }
     }
 }
 
@@ -45,6 +306,13 @@ impl From<f64> for RunOptions {
         RunOptions {
             time_limit,
             public_wall_time: true,
+            time_source: TimeSource::Wall,
+            clock: Arc::new(MonotonicClock::new()),
+            parallelism: 1,
+            memory_limit_kb: None,
+            json_output: true,
+            subtasks: Vec::new(),
+            log_level: None,
         }
     }
 }
@@ -54,6 +322,13 @@ impl Default for RunOptions {
         RunOptions {
             time_limit: 1.0,
             public_wall_time: true,
+            time_source: TimeSource::Wall,
+            clock: Arc::new(MonotonicClock::new()),
+            parallelism: 1,
+            memory_limit_kb: None,
+            json_output: true,
+            subtasks: Vec::new(),
+            log_level: None,
         }
     }
 }
@@ -103,6 +378,227 @@ fn get_multiplier(title: &str) -> u64 {
     1
 }
 
+/// Resource usage and exit status collected from a sandboxed solution run.
+#[derive(Debug, Clone)]
+pub struct ProcessUsage {
+    pub cpu_time: f64,
+    pub peak_memory_kb: u64,
+    pub exit_code: Option<i32>,
+    pub signal: Option<i32>,
+}
+
+/// Captured output and usage from a [`run_solution`] invocation.
+#[derive(Debug, Clone)]
+pub struct RunResult {
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+    pub usage: ProcessUsage,
+}
+
+/// Runs a contestant `command` under POSIX resource limits, feeding it
+/// `stdin_data` and capturing stdout/stderr, then recovers peak RSS and
+/// consumed CPU time via `wait4`/`getrusage` so `check_fn` implementations
+/// get real sandboxing instead of reimplementing process spawning.
+pub fn run_solution(
+    mut command: Command,
+    stdin_data: &[u8],
+    memory_limit_kb: Option<u64>,
+    cpu_limit_secs: Option<u64>,
+) -> Result<RunResult> {
+    command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    // SAFETY: the closure only calls async-signal-safe libc functions before
+    // exec, as required by `pre_exec`.
+    unsafe {
+        command.pre_exec(move || {
+            if let Some(kb) = memory_limit_kb {
+                let limit = libc::rlimit {
+                    rlim_cur: kb * 1024,
+                    rlim_max: kb * 1024,
+                };
+                if libc::setrlimit(libc::RLIMIT_AS, &limit) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                if libc::setrlimit(libc::RLIMIT_DATA, &limit) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+            }
+            if let Some(secs) = cpu_limit_secs {
+                let limit = libc::rlimit {
+                    rlim_cur: secs,
+                    rlim_max: secs,
+                };
+                if libc::setrlimit(libc::RLIMIT_CPU, &limit) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+            }
+            Ok(())
+        });
+    }
+
+    let mut child = command.spawn()?;
+    let mut stdin_pipe = child.stdin.take().ok_or("Failed to open solution stdin")?;
+    let mut stdout_pipe = child
+        .stdout
+        .take()
+        .ok_or("Failed to open solution stdout")?;
+    let mut stderr_pipe = child
+        .stderr
+        .take()
+        .ok_or("Failed to open solution stderr")?;
+
+    // Writing stdin and draining stdout/stderr all happen on their own
+    // threads, concurrently with each other and with `wait4` below: if
+    // `stdin_data` is larger than the pipe buffer and the child doesn't
+    // drain it before writing its own output, a single-threaded
+    // write-then-read would deadlock with both ends blocked on a full pipe.
+    let stdin_data = stdin_data.to_vec();
+    let stdin_thread =
+        std::thread::spawn(move || -> std::io::Result<()> { stdin_pipe.write_all(&stdin_data) });
+    let stdout_thread = std::thread::spawn(move || -> std::io::Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        stdout_pipe.read_to_end(&mut buf)?;
+        Ok(buf)
+    });
+    let stderr_thread = std::thread::spawn(move || -> std::io::Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        stderr_pipe.read_to_end(&mut buf)?;
+        Ok(buf)
+    });
+
+    let pid = child.id() as libc::pid_t;
+    let mut status: libc::c_int = 0;
+    let mut rusage: libc::rusage = unsafe { std::mem::zeroed() };
+    // SAFETY: `pid` came from the child we just spawned, and `status`/`rusage`
+    // are valid out-pointers sized for this platform's libc.
+    let wait_rc = unsafe { libc::wait4(pid, &mut status, 0, &mut rusage) };
+    if wait_rc < 0 {
+        return Err("wait4 failed while waiting for the solution process".into());
+    }
+
+    // The child may exit without reading all of stdin (e.g. it ignores
+    // stdin entirely), which makes the writer thread's `write_all` fail
+    // with a broken-pipe error; that's expected once the process is gone,
+    // not a real failure, so it's intentionally not propagated.
+    let _ = stdin_thread
+        .join()
+        .map_err(|_| "stdin writer thread panicked")?;
+    let stdout = stdout_thread
+        .join()
+        .map_err(|_| "stdout reader thread panicked")??;
+    let stderr = stderr_thread
+        .join()
+        .map_err(|_| "stderr reader thread panicked")??;
+
+    let exit_code = if libc::WIFEXITED(status) {
+        Some(libc::WEXITSTATUS(status))
+    } else {
+        None
+    };
+    let signal = if libc::WIFSIGNALED(status) {
+        Some(libc::WTERMSIG(status))
+    } else {
+        None
+    };
+
+    Ok(RunResult {
+        stdout,
+        stderr,
+        usage: ProcessUsage {
+            cpu_time: rusage.ru_utime.tv_sec as f64
+                + rusage.ru_utime.tv_usec as f64 / 1e6
+                + rusage.ru_stime.tv_sec as f64
+                + rusage.ru_stime.tv_usec as f64 / 1e6,
+            peak_memory_kb: rusage.ru_maxrss as u64,
+            exit_code,
+            signal,
+        },
+    })
+}
+
+/// Ordered, forward-only migration steps for the `submissions` database.
+/// Each entry is applied at most once, in a transaction, and recorded in
+/// `schema_version` so fresh deployments need no manual DDL and repeated
+/// runs against an already-migrated database are no-ops.
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE IF NOT EXISTS submissions (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        user_id TEXT NOT NULL,
+        problem TEXT NOT NULL,
+        address TEXT NOT NULL,
+        subtime TEXT NOT NULL,
+        score INTEGER NOT NULL,
+        multiplier INTEGER NOT NULL,
+        source BLOB NOT NULL
+    )",
+    "CREATE INDEX IF NOT EXISTS idx_submissions_problem ON submissions (problem);
+     CREATE INDEX IF NOT EXISTS idx_submissions_user_id ON submissions (user_id);",
+    "ALTER TABLE submissions ADD COLUMN cpu_time REAL;
+     ALTER TABLE submissions ADD COLUMN peak_memory INTEGER;
+     ALTER TABLE submissions ADD COLUMN verdict_json TEXT;",
+];
+
+/// Brings `conn`'s schema up to date by applying any [`MIGRATIONS`] steps
+/// not yet recorded in `schema_version`. Safe to call on every connection
+/// open: already-applied steps are skipped.
+pub fn migrate(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER NOT NULL,
+            applied_at TEXT NOT NULL
+        )",
+    )?;
+
+    let current_version: i64 = conn.query_row(
+        "SELECT COALESCE(MAX(version), 0) FROM schema_version",
+        [],
+        |row| row.get(0),
+    )?;
+
+    for (i, step) in MIGRATIONS.iter().enumerate() {
+        let version = (i + 1) as i64;
+        if version <= current_version {
+            continue;
+        }
+        let tx = conn.unchecked_transaction()?;
+        tx.execute_batch(step)?;
+        tx.execute(
+            "INSERT INTO schema_version (version, applied_at) VALUES (?1, ?2)",
+            params![
+                version,
+                chrono::Local::now()
+                    .format("%Y-%m-%d %H:%M:%S%.6f")
+                    .to_string()
+            ],
+        )?;
+        tx.commit()?;
+    }
+
+    Ok(())
+}
+
+/// Outcome of running a single test case, produced on whichever worker
+/// thread picked it up and collected back on the main thread for ordered
+/// reporting.
+enum CaseOutcome {
+    Verdict {
+        verdict: Verdict,
+        wall_time: f64,
+        cpu_time: f64,
+    },
+    CheckError(String),
+    GenError(String),
+}
+
+struct CaseRecord {
+    tc_n: usize,
+    group: String,
+    outcome: CaseOutcome,
+}
+
 pub fn run_tc<I, G, C, T, U, S, V, O>(
     options: O,
     init_fn: I,
@@ -112,100 +608,282 @@ pub fn run_tc<I, G, C, T, U, S, V, O>(
 ) -> Result<()>
 where
     O: Into<RunOptions>,
-    S: IntoIterator<Item = T>,
+    S: IntoIterator<Item = (String, T)>,
     V: Into<Verdict>,
+    T: Send,
+    U: Send,
     I: FnOnce(Option<&str>) -> Result<S>,
-    G: Fn(T) -> Result<U>,
-    C: Fn(U) -> Result<V>,
+    G: Fn(T) -> Result<U> + Sync,
+    C: Fn(U) -> Result<V> + Sync,
 {
     let options = options.into();
+    if let Some(level) = options.log_level.or_else(|| {
+        env::var("TAL_LOG_LEVEL")
+            .ok()
+            .and_then(|s| s.parse::<LevelFilter>().ok())
+    }) {
+        log::set_max_level(level);
+    }
     let subtask = fetch_env("TAL_size").ok();
     let output_dir = fetch_env("TAL_META_OUTPUT_FILES")?;
     let mut fout = File::create(format!("{output_dir}/result.txt"))?;
-    let mut tc_ok = 0;
-    let mut tc_n = 0;
     let iter = init_fn(subtask.as_deref())?.into_iter();
     let total_tc_n = match iter.size_hint() {
         (n, Some(m)) if n == m => n,
         _ => return Err("Cannot get the number of test cases".into()),
     };
+    // This integer is the machine-readable protocol handshake the outer
+    // runner parses; it must stay on stdout with nothing else interleaved.
+    // All other diagnostics go through `log` instead.
     println!("{}", total_tc_n);
     stdout().flush()?;
-    for tc_param in iter {
-        tc_n += 1;
-        let tc = gen_fn(tc_param)?;
-        stdout().flush()?;
-        let start = Instant::now();
-        let verdict = match check_fn(tc) {
-            Ok(x) => x.into(),
-            Err(e) => {
+
+    let tc_params: Vec<(usize, String, T)> = iter
+        .enumerate()
+        .map(|(i, (group, p))| (i + 1, group, p))
+        .collect();
+    let pool = TokenPool::new(options.parallelism);
+    let records: Mutex<Vec<CaseRecord>> = Mutex::new(Vec::with_capacity(tc_params.len()));
+
+    std::thread::scope(|scope| {
+        for (tc_n, group, tc_param) in tc_params {
+            let token = pool.acquire();
+            let gen_fn = &gen_fn;
+            let check_fn = &check_fn;
+            let options = &options;
+            let records = &records;
+            scope.spawn(move || {
+                let _token = token;
+                log::trace!("tc {tc_n} ({group}): generating");
+                let outcome = match gen_fn(tc_param) {
+                    Err(e) => CaseOutcome::GenError(e.to_string()),
+                    Ok(tc) => {
+                        let wall_start = options.clock.now();
+                        let cpu_start = options.clock.cpu_time();
+                        match check_fn(tc) {
+                            Err(e) => CaseOutcome::CheckError(e.to_string()),
+                            Ok(verdict) => {
+                                let wall_time = options.clock.now() - wall_start;
+                                let cpu_time = options.clock.cpu_time() - cpu_start;
+                                CaseOutcome::Verdict {
+                                    verdict: verdict.into(),
+                                    wall_time,
+                                    cpu_time,
+                                }
+                            }
+                        }
+                    }
+                };
+                records.lock().unwrap().push(CaseRecord {
+                    tc_n,
+                    group,
+                    outcome,
+                });
+            });
+        }
+    });
+
+    let mut records = records.into_inner().unwrap();
+    records.sort_by_key(|r| r.tc_n);
+
+    let mut tc_ok = 0;
+    let mut tc_n = 0;
+    let mut cases = Vec::with_capacity(records.len());
+    for record in records {
+        tc_n = record.tc_n;
+        let group = record.group;
+        let (verdict_kind, wall_time, cpu_time, peak_memory_kb, message) = match record.outcome {
+            CaseOutcome::GenError(e) => return Err(e.into()),
+            CaseOutcome::CheckError(e) => {
                 writeln!(fout, "Case #{tc_n:03}: RE")?;
-                eprintln!("Check error: {}", e);
-                continue;
+                log::warn!("tc {tc_n} ({group}): check error, skipping as RE: {e}");
+                (VerdictKind::Re, 0.0, 0.0, None, None)
             }
-        };
-        let elapsed = Instant::now().duration_since(start).as_secs_f64();
-        let mut p_verdict = |acr: &str| -> Result<()> {
-            use std::fmt::Write;
-            let mut verdict = String::new();
-            write!(verdict, "Case #{tc_n:03}: {}", acr)?;
-            if options.public_wall_time {
-                write!(verdict, " | Time: {:.3}s", elapsed)?;
+            CaseOutcome::Verdict {
+                verdict,
+                wall_time,
+                cpu_time,
+            } => {
+                let decisive_time = match options.time_source {
+                    TimeSource::Wall => wall_time,
+                    TimeSource::Cpu => cpu_time,
+                };
+                let exceeds_memory_limit = match (options.memory_limit_kb, verdict.peak_memory_kb) {
+                    (Some(limit), Some(peak)) => peak > limit,
+                    _ => false,
+                };
+                let verdict_kind = if decisive_time > options.time_limit {
+                    VerdictKind::Tle
+                } else if exceeds_memory_limit {
+                    VerdictKind::Mle
+                } else if verdict.ok {
+                    tc_ok += 1;
+                    VerdictKind::Ac
+                } else {
+                    VerdictKind::Wa
+                };
+
+                let mut line = format!("Case #{tc_n:03}: {}", verdict_kind);
+                if options.public_wall_time {
+                    use std::fmt::Write;
+                    write!(line, " | Time: {:.3}s | CPU: {:.3}s", wall_time, cpu_time)?;
+                }
+                if let Some(peak) = verdict.peak_memory_kb {
+                    use std::fmt::Write;
+                    write!(line, " | Mem: {}KB", peak)?;
+                }
+                writeln!(fout, "{}", line)?;
+                log::info!("tc {tc_n} ({group}): {verdict_kind} | wall {wall_time:.3}s | cpu {cpu_time:.3}s");
+                if let Some(msg) = &verdict.msg {
+                    writeln!(fout)?;
+                    writeln!(fout, "{}", msg)?;
+                    writeln!(fout)?;
+                }
+
+                (
+                    verdict_kind,
+                    wall_time,
+                    cpu_time,
+                    verdict.peak_memory_kb,
+                    verdict.msg,
+                )
             }
-            writeln!(fout, "{}", verdict)?;
-            Ok(())
         };
-        if elapsed > options.time_limit {
-            p_verdict("TLE")?;
-        } else if verdict.ok {
-            p_verdict("AC")?;
-            tc_ok += 1;
-        } else {
-            p_verdict("WA")?;
-        }
-        if let Some(msg) = verdict.msg {
-            writeln!(fout)?;
-            writeln!(fout, "{}", msg)?;
-            writeln!(fout)?;
-        }
+        cases.push(CaseReport {
+            index: tc_n,
+            verdict: verdict_kind,
+            wall_time,
+            cpu_time,
+            peak_memory_kb,
+            subtask: Some(group),
+            message,
+        });
     }
     writeln!(fout)?;
     writeln!(fout, "Score: {}/{}", tc_ok, tc_n)?;
+
+    let mut seen_groups = Vec::new();
+    for case in &cases {
+        let group = case.subtask.clone().unwrap_or_default();
+        if !seen_groups.contains(&group) {
+            seen_groups.push(group);
+        }
+    }
+    let subtask_scores: Vec<SubtaskScore> = seen_groups
+        .into_iter()
+        .map(|group| {
+            let points = options
+                .subtasks
+                .iter()
+                .find(|s| s.name == group)
+                .map(|s| s.points)
+                .unwrap_or(0.0);
+            let all_passed = cases
+                .iter()
+                .filter(|c| c.subtask.as_deref() == Some(group.as_str()))
+                .all(|c| c.verdict == VerdictKind::Ac);
+            let achieved = if all_passed { points } else { 0.0 };
+            SubtaskScore {
+                name: group,
+                points,
+                achieved,
+                all_passed,
+            }
+        })
+        .collect();
+    let subtask_total: f64 = subtask_scores.iter().map(|s| s.achieved).sum();
+
+    if !options.subtasks.is_empty() {
+        writeln!(fout)?;
+        writeln!(fout, "Subtasks:")?;
+        for s in &subtask_scores {
+            writeln!(
+                fout,
+                "  {}: {:.1}/{:.1}{}",
+                s.name,
+                s.achieved,
+                s.points,
+                if s.all_passed { "" } else { " (failed)" }
+            )?;
+        }
+        writeln!(fout, "Subtask score: {:.1}", subtask_total)?;
+    }
+
+    let mut multiplier = None;
     if valid_points {
         match (
             fetch_env("TAL_META_EXP_TOKEN"),
             fetch_env("TAL_EXT_EXAM_DB"),
         ) {
             (Ok(token), Ok(db_path)) => {
-                let conn = Connection::open(db_path)?;
+                let conn = Connection::open(&db_path).map_err(|e| {
+                    log::error!("Failed to open submissions DB at {db_path}: {e}");
+                    e
+                })?;
+                migrate(&conn).map_err(|e| {
+                    log::error!("Failed to migrate submissions DB at {db_path}: {e}");
+                    e
+                })?;
                 let problem = fetch_env("TAL_META_CODENAME")?;
                 let address = fetch_env("TAL_META_EXP_ADDRESS")?;
                 let source = fs::read(format!("{}/source", fetch_env("TAL_META_INPUT_FILES")?))?;
+                let problem_multiplier = get_multiplier(&problem);
+                multiplier = Some(problem_multiplier);
+                let score = if options.subtasks.is_empty() {
+                    tc_ok as i64
+                } else {
+                    (subtask_total * problem_multiplier as f64).round() as i64
+                };
+                let total_cpu_time: f64 = cases.iter().map(|c| c.cpu_time).sum();
+                let peak_memory = cases.iter().filter_map(|c| c.peak_memory_kb).max();
+                let verdict_json = serde_json::to_string(&cases)?;
 
                 conn.execute(
-                "INSERT INTO submissions (user_id, problem, address, subtime, score, multiplier, source) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                "INSERT INTO submissions (user_id, problem, address, subtime, score, multiplier, source, cpu_time, peak_memory, verdict_json) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
                 params![
                     token,
                     problem,
                     address,
                     chrono::Local::now().format("%Y-%m-%d %H:%M:%S%.6f").to_string(),
-                    tc_ok,
-                    get_multiplier(&problem),
-                    source
+                    score,
+                    problem_multiplier,
+                    source,
+                    total_cpu_time,
+                    peak_memory,
+                    verdict_json
                 ],
-            )?;
+            ).map_err(|e| {
+                log::error!("Failed to record submission for problem {problem}: {e}");
+                e
+            })?;
             }
             _ => {}
         };
     }
+
+    if options.json_output {
+        let summary = RunSummary {
+            tc_ok,
+            tc_n,
+            multiplier,
+            subtask_scores,
+            cases,
+        };
+        let json = serde_json::to_string_pretty(&summary)?;
+        fs::write(format!("{output_dir}/result.json"), json)?;
+    }
+
     Ok(())
 }
 
-pub fn gen_data<T: Clone>(subtask: &str, data: &[(&str, usize, T)]) -> Vec<T> {
+/// Builds the test-case parameter list for a subtask selection, pairing each
+/// parameter with the name of the group it belongs to so `run_tc` can score
+/// by group instead of by raw AC count.
+pub fn gen_data<T: Clone>(subtask: &str, data: &[(&str, usize, T)]) -> Vec<(String, T)> {
     let mut tc = Vec::new();
     for (name, n, v) in data {
         for _ in 0..*n {
-            tc.push(v.clone());
+            tc.push((name.to_string(), v.clone()));
         }
         if subtask == *name {
             break;
@@ -213,3 +891,51 @@ pub fn gen_data<T: Clone>(subtask: &str, data: &[(&str, usize, T)]) -> Vec<T> {
     }
     tc
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Drives `run_tc` through a scripted `TestClock` to prove that TLE
+    /// decisions depend only on the injected readings, not on real wall-clock
+    /// jitter.
+    #[test]
+    fn tle_decision_is_deterministic_with_scripted_clock() {
+        let output_dir =
+            std::env::temp_dir().join(format!("tal_utils_rs_test_{}", std::process::id()));
+        fs::create_dir_all(&output_dir).unwrap();
+        env::set_var("TAL_META_OUTPUT_FILES", &output_dir);
+        env::remove_var("TAL_size");
+
+        let clock = Arc::new(TestClock::new(
+            vec![0.0, 0.05, 0.0, 0.2],
+            vec![0.0, 0.0, 0.0, 0.0],
+        ));
+        let options = RunOptions {
+            time_limit: 0.1,
+            public_wall_time: true,
+            time_source: TimeSource::Wall,
+            clock,
+            parallelism: 1,
+            memory_limit_kb: None,
+            json_output: false,
+            subtasks: Vec::new(),
+            log_level: None,
+        };
+
+        run_tc(
+            options,
+            |_| Ok(vec![("group".to_string(), ()), ("group".to_string(), ())]),
+            |_: ()| Ok(()),
+            |_: ()| Ok(true),
+            false,
+        )
+        .unwrap();
+
+        let result = fs::read_to_string(output_dir.join("result.txt")).unwrap();
+        fs::remove_dir_all(&output_dir).ok();
+
+        assert!(result.contains("Case #001: AC"));
+        assert!(result.contains("Case #002: TLE"));
+    }
+}